@@ -122,6 +122,10 @@ impl ProxyInfo {
         self.port
     }
 
+    pub fn country_code(&self) -> CountryCode {
+        self.country_code
+    }
+
     pub fn country_name(&self) -> &str {
         &self.country_name
     }
@@ -130,6 +134,14 @@ impl ProxyInfo {
         self.anonymity
     }
 
+    pub fn google(&self) -> bool {
+        self.google
+    }
+
+    pub fn https(&self) -> bool {
+        self.https
+    }
+
     pub fn get_url(&self) -> String {
         let protocol = if self.https { "https" } else { "http" };
         format!("{}://{}:{}", protocol, self.ip, self.port)
@@ -0,0 +1,254 @@
+use free_proxy_list::{
+    Anonymity,
+    CountryCode,
+    ProxyInfo,
+    ProxyInfoError,
+};
+use proptest::prelude::*;
+use select::{
+    document::Document,
+    predicate::Name,
+};
+use std::net::IpAddr;
+
+const IP: usize = 0;
+const PORT: usize = 1;
+const COUNTRY: usize = 2;
+const COUNTRY_NAME: usize = 3;
+const ANONYMITY: usize = 4;
+const GOOGLE: usize = 5;
+const HTTPS: usize = 6;
+
+#[derive(Debug, Clone)]
+struct Row {
+    ip: IpAddr,
+    port: u32,
+    country_code: CountryCode,
+    country_name: String,
+    anonymity: Anonymity,
+    google: bool,
+    https: bool,
+}
+
+impl Row {
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.ip.to_string(),
+            self.port.to_string(),
+            self.country_code.alpha2().to_string(),
+            self.country_name.clone(),
+            anonymity_str(self.anonymity).to_string(),
+            yes_no(self.google).to_string(),
+            yes_no(self.https).to_string(),
+            "1 minute ago".to_string(), // Last checked, ignored by the parser
+        ]
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    /// Drop this column and every column after it
+    Truncate(usize),
+    /// Keep the column but remove its text
+    Empty(usize),
+    /// Replace the column's text with an unparsable value
+    Invalid(usize, String),
+}
+
+impl Mutation {
+    fn apply(&self, mut cells: Vec<String>) -> Vec<String> {
+        match self {
+            Mutation::Truncate(column) => cells.truncate(*column),
+            Mutation::Empty(column) => cells[*column].clear(),
+            Mutation::Invalid(column, value) => cells[*column] = value.clone(),
+        }
+        cells
+    }
+
+    fn matches(&self, e: &ProxyInfoError) -> bool {
+        match self {
+            Mutation::Truncate(column) | Mutation::Empty(column) => is_missing(*column, e),
+            Mutation::Invalid(column, _) => is_invalid(*column, e),
+        }
+    }
+}
+
+fn is_missing(column: usize, e: &ProxyInfoError) -> bool {
+    matches!(
+        (column, e),
+        (IP, ProxyInfoError::MissingIp)
+            | (PORT, ProxyInfoError::MissingPort)
+            | (COUNTRY, ProxyInfoError::MissingCountry)
+            | (COUNTRY_NAME, ProxyInfoError::MissingCountryName)
+            | (ANONYMITY, ProxyInfoError::MissingAnonymity)
+            | (GOOGLE, ProxyInfoError::MissingGoogle)
+            | (HTTPS, ProxyInfoError::MissingHttps)
+    )
+}
+
+fn is_invalid(column: usize, e: &ProxyInfoError) -> bool {
+    matches!(
+        (column, e),
+        (IP, ProxyInfoError::InvalidIp(_))
+            | (PORT, ProxyInfoError::InvalidPort(_))
+            | (COUNTRY, ProxyInfoError::InvalidCountry(_))
+            | (ANONYMITY, ProxyInfoError::InvalidAnonymity(_))
+            | (GOOGLE, ProxyInfoError::InvalidGoogle(_))
+            | (HTTPS, ProxyInfoError::InvalidHttps(_))
+    )
+}
+
+fn anonymity_str(anonymity: Anonymity) -> &'static str {
+    match anonymity {
+        Anonymity::Transparent => "transparent",
+        Anonymity::EliteProxy => "elite proxy",
+        Anonymity::Anonymous => "anonymous",
+    }
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+fn render_table<T: AsRef<[String]>>(rows: &[T]) -> String {
+    let mut html = String::from("<table id=\"proxylisttable\"><tbody>");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row.as_ref() {
+            html.push_str("<td>");
+            html.push_str(cell);
+            html.push_str("</td>");
+        }
+        html.push_str("</tr>");
+    }
+    html.push_str("</tbody></table>");
+    html
+}
+
+fn parse_table(html: &str) -> Vec<Result<ProxyInfo, ProxyInfoError>> {
+    let doc = Document::from(html);
+    doc.find(Name("tr")).map(ProxyInfo::from_node).collect()
+}
+
+fn row_strategy() -> impl Strategy<Value = Row> {
+    (
+        any::<IpAddr>(),
+        any::<u32>(),
+        proptest::sample::select(CountryCode::as_array().to_vec()),
+        "[A-Za-z]([A-Za-z .,]{0,20}[A-Za-z])?",
+        prop_oneof![
+            Just(Anonymity::Transparent),
+            Just(Anonymity::EliteProxy),
+            Just(Anonymity::Anonymous),
+        ],
+        any::<bool>(),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(ip, port, country_code, country_name, anonymity, google, https)| Row {
+                ip,
+                port,
+                country_code,
+                country_name,
+                anonymity,
+                google,
+                https,
+            },
+        )
+}
+
+fn invalid_value_strategy(column: usize) -> BoxedStrategy<String> {
+    match column {
+        IP => prop_oneof![
+            "[a-z]{1,8}",
+            Just("256.0.0.1".to_string()),
+            Just("1.2.3".to_string()),
+        ]
+        .boxed(),
+        PORT => prop_oneof![
+            "[a-z]{1,8}",
+            Just("-1".to_string()),
+            Just("4294967296".to_string()),
+        ]
+        .boxed(),
+        COUNTRY => proptest::sample::select(vec!["ZZ", "XX", "QQ", "USA", "1"])
+            .prop_map(String::from)
+            .boxed(),
+        ANONYMITY => "[a-z]{1,12}"
+            .prop_filter("must not be a valid anonymity", |s| {
+                s.parse::<Anonymity>().is_err()
+            })
+            .boxed(),
+        GOOGLE | HTTPS => "[a-z]{1,5}"
+            .prop_filter("must not be yes or no", |s| s != "yes" && s != "no")
+            .boxed(),
+        _ => unreachable!("column {} has no invalid values", column),
+    }
+}
+
+fn mutation_strategy() -> impl Strategy<Value = Mutation> {
+    let invalid = proptest::sample::select(vec![IP, PORT, COUNTRY, ANONYMITY, GOOGLE, HTTPS])
+        .prop_flat_map(|column| {
+            invalid_value_strategy(column).prop_map(move |value| Mutation::Invalid(column, value))
+        });
+
+    prop_oneof![
+        (IP..=HTTPS).prop_map(Mutation::Truncate),
+        (IP..=HTTPS).prop_map(Mutation::Empty),
+        invalid,
+    ]
+}
+
+proptest! {
+    #[test]
+    fn valid_rows_round_trip(rows in proptest::collection::vec(row_strategy(), 1..8)) {
+        let html = render_table(&rows.iter().map(Row::cells).collect::<Vec<_>>());
+        let parsed = parse_table(&html);
+        prop_assert_eq!(parsed.len(), rows.len());
+
+        for (row, info) in rows.iter().zip(parsed.iter()) {
+            let info = match info {
+                Ok(info) => info,
+                Err(e) => return Err(TestCaseError::fail(format!("failed to parse {:?}: {:?}", row, e))),
+            };
+
+            prop_assert_eq!(info.ip(), &row.ip);
+            prop_assert_eq!(info.port(), row.port);
+            prop_assert_eq!(info.country_code(), row.country_code);
+            prop_assert_eq!(info.country_name(), row.country_name.as_str());
+            prop_assert_eq!(info.anonymity(), row.anonymity);
+            prop_assert_eq!(info.google(), row.google);
+            prop_assert_eq!(info.https(), row.https);
+        }
+    }
+
+    #[test]
+    fn mutated_rows_map_to_errors(row in row_strategy(), mutation in mutation_strategy()) {
+        let html = render_table(&[mutation.apply(row.cells())]);
+        let parsed = parse_table(&html);
+        prop_assert_eq!(parsed.len(), 1);
+
+        match &parsed[0] {
+            Ok(info) => {
+                return Err(TestCaseError::fail(format!("{:?} parsed as {:?}", mutation, info)));
+            }
+            Err(e) => prop_assert!(mutation.matches(e), "{:?} gave {:?}", mutation, e),
+        }
+    }
+
+    #[test]
+    fn arbitrary_markup_does_not_panic(html in any::<String>()) {
+        parse_table(&html);
+    }
+
+    #[test]
+    fn arbitrary_table_does_not_panic(
+        html in "(<table>|<tbody>|<tr>|</tr>|<td>|</td>|<td></td>|[a-z0-9 .:]{0,8}){0,40}"
+    ) {
+        parse_table(&html);
+    }
+}
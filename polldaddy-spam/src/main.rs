@@ -9,10 +9,34 @@ use crate::{
     },
 };
 use futures::FutureExt;
-use polldaddy::Quiz;
-use std::time::Duration;
+use polldaddy::{
+    Catalog,
+    Quiz,
+};
+use std::{
+    path::PathBuf,
+    time::Duration,
+};
 use tokio::runtime::Runtime;
 
+/// Env var that overrides the poll catalog location
+const CATALOG_PATH_VAR: &str = "POLLDADDY_CATALOG";
+const CATALOG_FILE_NAME: &str = "polldaddy-polls.json";
+
+/// Get the poll catalog path.
+///
+/// Uses `POLLDADDY_CATALOG` if set, otherwise a file in the user's home dir, falling back to the current dir.
+fn catalog_path() -> PathBuf {
+    if let Some(path) = std::env::var_os(CATALOG_PATH_VAR) {
+        return PathBuf::from(path);
+    }
+
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(format!(".{}", CATALOG_FILE_NAME)))
+        .unwrap_or_else(|| PathBuf::from(CATALOG_FILE_NAME))
+}
+
 fn proxy_spam(ctx: &mut AppContext) {
     let quiz = ctx.quiz.clone();
     let option = ctx.option;
@@ -194,6 +218,38 @@ fn main() {
         return;
     }
 
+    let catalog_path = catalog_path();
+    match Catalog::open(&catalog_path) {
+        Ok(mut catalog) => {
+            let mut recorded = 0;
+            for quiz in quizzes.iter().filter_map(|q| q.as_ref().ok()) {
+                catalog.record(quiz);
+                recorded += 1;
+            }
+
+            match catalog.save() {
+                Ok(()) => {
+                    println!(
+                        "Recorded {} poll(s). Catalog now has {} poll(s): {}",
+                        recorded,
+                        catalog.len(),
+                        catalog.path().display()
+                    );
+                }
+                Err(e) => {
+                    println!("Failed to save poll catalog, got error: {:#?}", e);
+                }
+            }
+        }
+        Err(e) => {
+            println!(
+                "Failed to open poll catalog at {}, got error: {:#?}",
+                catalog_path.display(),
+                e
+            );
+        }
+    }
+
     let maybe_quiz = quizzes.swap_remove(0);
 
     let quiz = match maybe_quiz {
@@ -0,0 +1,575 @@
+use crate::{
+    util::get_time_ms,
+    Quiz,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::{
+        btree_map::Entry,
+        BTreeMap,
+        BTreeSet,
+    },
+    fs::File,
+    io::Write,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+use url::Url;
+
+/// The current version of the catalog file format
+pub const CATALOG_VERSION: u32 = 1;
+
+pub type CatalogResult<T> = Result<T, CatalogError>;
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+}
+
+impl From<std::io::Error> for CatalogError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CatalogError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct CatalogHeader {
+    version: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CatalogFile<P> {
+    version: u32,
+    polls: P,
+}
+
+/// A json-file backed record of every poll that has been discovered, keyed by poll id.
+pub struct Catalog {
+    path: PathBuf,
+    polls: BTreeMap<u32, PollRecord>,
+}
+
+impl Catalog {
+    /// Load the catalog at the given path. A missing file is treated as an empty catalog.
+    pub fn open<P: Into<PathBuf>>(path: P) -> CatalogResult<Self> {
+        let path = path.into();
+        let polls = Self::load(&path)?;
+
+        Ok(Catalog { path, polls })
+    }
+
+    fn load(path: &Path) -> CatalogResult<BTreeMap<u32, PollRecord>> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let header: CatalogHeader = serde_json::from_slice(&data)?;
+        if header.version != CATALOG_VERSION {
+            return Err(CatalogError::UnsupportedVersion(header.version));
+        }
+
+        let mut file: CatalogFile<BTreeMap<u32, PollRecord>> = serde_json::from_slice(&data)?;
+        for record in file.polls.values_mut() {
+            record.rebuild_domains();
+        }
+
+        Ok(file.polls)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.polls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.polls.is_empty()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&PollRecord> {
+        self.polls.get(&id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PollRecord> {
+        self.polls.values()
+    }
+
+    /// Record a sighting of a quiz at the current time.
+    pub fn record(&mut self, quiz: &Quiz) {
+        self.record_at(quiz, get_time_ms());
+    }
+
+    /// Record a sighting of a quiz at the given unix time, in ms.
+    ///
+    /// Sightings may be recorded out of order.
+    /// The poll's hash, va, and closed status are only updated by the newest sighting.
+    pub fn record_at(&mut self, quiz: &Quiz, time: u128) {
+        let answers = quiz
+            .get_answers()
+            .iter()
+            .map(|a| AnswerRecord {
+                id: a.get_id(),
+                text: a.get_text().to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let record = self
+            .polls
+            .entry(quiz.get_id())
+            .or_insert_with(|| PollRecord {
+                id: quiz.get_id(),
+                hash: quiz.get_hash().to_string(),
+                va: quiz.get_va().to_string(),
+                closed: quiz.is_closed(),
+                referers: BTreeSet::new(),
+                domains: BTreeSet::new(),
+                answer_history: Vec::new(),
+                first_seen: time,
+                last_seen: time,
+            });
+
+        if time >= record.last_seen {
+            record.hash = quiz.get_hash().to_string();
+            record.va = quiz.get_va().to_string();
+            record.closed = quiz.is_closed();
+        }
+
+        record.insert_referer(quiz.get_referer().to_string());
+
+        record.first_seen = record.first_seen.min(time);
+        record.last_seen = record.last_seen.max(time);
+
+        record.insert_answers(time, answers);
+    }
+
+    /// Merge the catalog on disk into this one, then write the result back to disk.
+    ///
+    /// The data is written and synced to a temporary file first and then renamed over the old catalog,
+    /// so an interrupted save does not corrupt existing data.
+    /// Merging keeps results saved by other processes since this catalog was opened,
+    /// but there is no lock, so a save that lands between another process's merge and rename may still be lost.
+    pub fn save(&mut self) -> CatalogResult<()> {
+        for (id, record) in Self::load(&self.path)? {
+            match self.polls.entry(id) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(record),
+                Entry::Vacant(entry) => {
+                    entry.insert(record);
+                }
+            }
+        }
+
+        let data = serde_json::to_vec_pretty(&CatalogFile {
+            version: CATALOG_VERSION,
+            polls: &self.polls,
+        })?;
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+
+        {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&data)?;
+            file.sync_all()?;
+        }
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Get all polls that were found on a page hosted on the given domain or one of its subdomains.
+    /// The domain is matched case-insensitively.
+    pub fn find_by_domain(&self, domain: &str) -> impl Iterator<Item = &PollRecord> {
+        let domain = domain.to_lowercase();
+        let suffix = format!(".{}", domain);
+        self.iter().filter(move |r| {
+            r.get_domains()
+                .any(|host| host == domain || host.ends_with(&suffix))
+        })
+    }
+
+    /// Get all polls with the given closed status, as of the last time they were seen.
+    pub fn find_by_status(&self, closed: bool) -> impl Iterator<Item = &PollRecord> {
+        self.iter().filter(move |r| r.is_closed() == closed)
+    }
+
+    /// Get all polls whose `first_seen..=last_seen` range overlaps `start..=end`, unix time in ms.
+    ///
+    /// Individual sightings are not stored,
+    /// so a poll may be returned even if it was not seen during the given range.
+    pub fn find_seen_overlapping(
+        &self,
+        start: u128,
+        end: u128,
+    ) -> impl Iterator<Item = &PollRecord> {
+        self.iter()
+            .filter(move |r| r.first_seen <= end && r.last_seen >= start)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollRecord {
+    id: u32,
+    hash: String,
+    va: String,
+    closed: bool,
+    referers: BTreeSet<String>,
+    /// Hosts of `referers`, rebuilt on load
+    #[serde(skip)]
+    domains: BTreeSet<String>,
+    answer_history: Vec<AnswerSnapshot>,
+    first_seen: u128,
+    last_seen: u128,
+}
+
+impl PollRecord {
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_hash(&self) -> &str {
+        &self.hash
+    }
+
+    pub fn get_va(&self) -> &str {
+        &self.va
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn get_referers(&self) -> impl Iterator<Item = &str> {
+        self.referers.iter().map(String::as_str)
+    }
+
+    /// Get the hosts of all referers this poll was found on. Unparsable referers are skipped.
+    pub fn get_domains(&self) -> impl Iterator<Item = &str> {
+        self.domains.iter().map(String::as_str)
+    }
+
+    /// Get the most recently seen answer list
+    pub fn get_answers(&self) -> Option<&[AnswerRecord]> {
+        self.answer_history.last().map(|s| s.answers.as_slice())
+    }
+
+    /// Get every distinct answer list that has been seen, oldest first
+    pub fn get_answer_history(&self) -> &[AnswerSnapshot] {
+        &self.answer_history
+    }
+
+    pub fn get_first_seen(&self) -> u128 {
+        self.first_seen
+    }
+
+    pub fn get_last_seen(&self) -> u128 {
+        self.last_seen
+    }
+
+    fn insert_referer(&mut self, referer: String) {
+        if let Some(host) = Url::parse(&referer).ok().as_ref().and_then(Url::host_str) {
+            self.domains.insert(host.to_string());
+        }
+        self.referers.insert(referer);
+    }
+
+    fn rebuild_domains(&mut self) {
+        let referers = std::mem::take(&mut self.referers);
+        self.domains.clear();
+        for referer in referers {
+            self.insert_referer(referer);
+        }
+    }
+
+    /// Merge another record of the same poll into this one
+    fn merge(&mut self, other: PollRecord) {
+        if other.last_seen > self.last_seen {
+            self.hash = other.hash;
+            self.va = other.va;
+            self.closed = other.closed;
+        }
+
+        for referer in other.referers {
+            self.insert_referer(referer);
+        }
+
+        self.first_seen = self.first_seen.min(other.first_seen);
+        self.last_seen = self.last_seen.max(other.last_seen);
+
+        for snapshot in other.answer_history {
+            self.insert_answers(snapshot.time, snapshot.answers);
+        }
+    }
+
+    /// Insert an answer list seen at the given time, keeping the history sorted and free of consecutive duplicates.
+    ///
+    /// An answer list seen at the same time as an existing snapshot replaces it.
+    fn insert_answers(&mut self, time: u128, answers: Vec<AnswerRecord>) {
+        let snapshot = AnswerSnapshot { time, answers };
+        match self.answer_history.iter().position(|s| s.time >= time) {
+            Some(i) if self.answer_history[i].time == time => self.answer_history[i] = snapshot,
+            Some(i) => self.answer_history.insert(i, snapshot),
+            None => self.answer_history.push(snapshot),
+        }
+
+        // Keep the earliest of each run, as that is when the answer list was first seen
+        self.answer_history
+            .dedup_by(|snapshot, prev| snapshot.answers == prev.answers);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerSnapshot {
+    time: u128,
+    answers: Vec<AnswerRecord>,
+}
+
+impl AnswerSnapshot {
+    /// The unix time, in ms, this answer list was first seen
+    pub fn get_time(&self) -> u128 {
+        self.time
+    }
+
+    pub fn get_answers(&self) -> &[AnswerRecord] {
+        &self.answers
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnswerRecord {
+    id: u32,
+    text: String,
+}
+
+impl AnswerRecord {
+    pub fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn quiz(id: u32, referer: &str, closed: bool, answers: &[(u32, &str)]) -> Quiz {
+        let answers = answers
+            .iter()
+            .map(|(id, text)| format!("['{}', '{}']", id, text))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let data = format!(
+            "var PDV_h{id} = 'hash'; var pollClosed{id} = {closed}; var PDV_va{id} = 'va'; var PDV_A{id} = [{answers}];",
+            id = id,
+            closed = closed,
+            answers = answers
+        );
+
+        Quiz::from_script_data(referer.to_string(), id, &data).unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "polldaddy-catalog-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn ids<'a>(iter: impl Iterator<Item = &'a PollRecord>) -> Vec<u32> {
+        iter.map(PollRecord::get_id).collect()
+    }
+
+    #[test]
+    fn unchanged_answers_do_not_add_snapshot() {
+        let mut catalog = Catalog::open(temp_path("unchanged")).unwrap();
+        let q = quiz(1, "https://example.com/", false, &[(10, "a"), (11, "b")]);
+        catalog.record_at(&q, 100);
+        catalog.record_at(&q, 200);
+
+        let record = catalog.get(1).unwrap();
+        assert_eq!(record.get_answer_history().len(), 1);
+        assert_eq!(record.get_answer_history()[0].get_time(), 100);
+        assert_eq!(
+            (record.get_first_seen(), record.get_last_seen()),
+            (100, 200)
+        );
+    }
+
+    #[test]
+    fn changed_answers_add_snapshot() {
+        let mut catalog = Catalog::open(temp_path("changed")).unwrap();
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(10, "a")]), 100);
+        catalog.record_at(
+            &quiz(1, "https://example.com/", true, &[(10, "a"), (11, "b")]),
+            200,
+        );
+
+        let record = catalog.get(1).unwrap();
+        let history = record.get_answer_history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].get_time(), 100);
+        assert_eq!(history[1].get_time(), 200);
+        assert_eq!(record.get_answers().unwrap().len(), 2);
+        assert!(record.is_closed());
+    }
+
+    #[test]
+    fn out_of_order_sightings_stay_sorted() {
+        let mut catalog = Catalog::open(temp_path("out-of-order")).unwrap();
+        catalog.record_at(&quiz(1, "https://example.com/", true, &[(11, "b")]), 100);
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(10, "a")]), 50);
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(11, "b")]), 75);
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(11, "b")]), 75);
+
+        let history = |catalog: &Catalog| {
+            catalog
+                .get(1)
+                .unwrap()
+                .get_answer_history()
+                .iter()
+                .map(|s| (s.get_time(), s.get_answers()[0].get_text().to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(history(&catalog), [(50, "a".into()), (75, "b".into())]);
+
+        // A different answer list seen in the same ms replaces the snapshot
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(12, "c")]), 75);
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(12, "c")]), 75);
+        assert_eq!(history(&catalog), [(50, "a".into()), (75, "c".into())]);
+
+        let record = catalog.get(1).unwrap();
+        assert_eq!(record.get_first_seen(), 50);
+        assert_eq!(record.get_last_seen(), 100);
+        assert!(record.is_closed());
+    }
+
+    #[test]
+    fn save_open_round_trip() {
+        let path = temp_path("round-trip");
+        let time = u128::from(u64::MAX) + 1;
+
+        let mut catalog = Catalog::open(&path).unwrap();
+        assert!(catalog.is_empty());
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[(10, "a")]), time);
+        catalog.record_at(
+            &quiz(1, "https://example.com/", false, &[(11, "b")]),
+            time + 1,
+        );
+        catalog.save().unwrap();
+
+        let catalog = Catalog::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let record = catalog.get(1).unwrap();
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(
+            (record.get_first_seen(), record.get_last_seen()),
+            (time, time + 1)
+        );
+        assert_eq!(record.get_answer_history().len(), 2);
+        assert_eq!(record.get_answer_history()[1].get_time(), time + 1);
+        assert_eq!(record.get_answers().unwrap()[0].get_text(), "b");
+        assert_eq!(
+            record.get_referers().collect::<Vec<_>>(),
+            ["https://example.com/"]
+        );
+        assert_eq!(record.get_domains().collect::<Vec<_>>(), ["example.com"]);
+    }
+
+    #[test]
+    fn save_merges_catalog_on_disk() {
+        let path = temp_path("merge");
+
+        let mut first = Catalog::open(&path).unwrap();
+        let mut second = Catalog::open(&path).unwrap();
+        first.record_at(&quiz(1, "https://example.com/", false, &[(10, "a")]), 100);
+        second.record_at(&quiz(1, "https://example.org/", true, &[(11, "b")]), 200);
+        second.record_at(&quiz(2, "https://example.org/", false, &[]), 200);
+        first.save().unwrap();
+        second.save().unwrap();
+
+        let catalog = Catalog::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(catalog.len(), 2);
+        let record = catalog.get(1).unwrap();
+        assert_eq!(record.get_answer_history().len(), 2);
+        assert_eq!(
+            (record.get_first_seen(), record.get_last_seen()),
+            (100, 200)
+        );
+        assert_eq!(record.get_domains().count(), 2);
+        assert!(record.is_closed());
+    }
+
+    #[test]
+    fn open_rejects_unknown_version() {
+        let path = temp_path("version");
+        std::fs::write(&path, r#"{"version": 9999, "polls": {}}"#).unwrap();
+
+        let result = Catalog::open(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(CatalogError::UnsupportedVersion(9999))
+        ));
+    }
+
+    #[test]
+    fn find_by_domain() {
+        let mut catalog = Catalog::open(temp_path("domain")).unwrap();
+        catalog.record_at(&quiz(1, "https://www.Example.com/page", false, &[]), 0);
+        catalog.record_at(&quiz(2, "https://example.org/", false, &[]), 0);
+        catalog.record_at(&quiz(3, "https://notexample.com/", false, &[]), 0);
+
+        assert_eq!(ids(catalog.find_by_domain("example.com")), [1]);
+        assert_eq!(ids(catalog.find_by_domain("www.Example.com")), [1]);
+        assert_eq!(ids(catalog.find_by_domain("EXAMPLE.ORG")), [2]);
+        assert!(ids(catalog.find_by_domain("com.example")).is_empty());
+    }
+
+    #[test]
+    fn find_by_status() {
+        let mut catalog = Catalog::open(temp_path("status")).unwrap();
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[]), 0);
+        catalog.record_at(&quiz(2, "https://example.com/", true, &[]), 0);
+
+        assert_eq!(ids(catalog.find_by_status(false)), [1]);
+        assert_eq!(ids(catalog.find_by_status(true)), [2]);
+    }
+
+    #[test]
+    fn find_seen_overlapping() {
+        let mut catalog = Catalog::open(temp_path("seen")).unwrap();
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[]), 50);
+        catalog.record_at(&quiz(1, "https://example.com/", false, &[]), 100);
+        catalog.record_at(&quiz(2, "https://example.com/", false, &[]), 200);
+
+        assert_eq!(ids(catalog.find_seen_overlapping(60, 70)), [1]);
+        assert_eq!(ids(catalog.find_seen_overlapping(100, 200)), [1, 2]);
+        assert_eq!(ids(catalog.find_seen_overlapping(150, 300)), [2]);
+        assert!(ids(catalog.find_seen_overlapping(0, 49)).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 pub use crate::{
+    catalog::CatalogError,
     nonce::NonceError,
     quiz::QuizError,
     vote_response::VoteResponseError,
@@ -1,3 +1,4 @@
+pub mod catalog;
 pub mod client;
 pub mod error;
 pub mod nonce;
@@ -6,8 +7,10 @@ pub mod util;
 pub mod vote_response;
 
 pub use crate::{
+    catalog::Catalog,
     client::Client,
     error::{
+        CatalogError,
         PollError,
         PollResult,
     },
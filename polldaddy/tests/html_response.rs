@@ -0,0 +1,307 @@
+use polldaddy::{
+    vote_response::html_response::{
+        AnswerResponseError,
+        HtmlResponseError,
+    },
+    HtmlResponse,
+};
+use proptest::prelude::*;
+use select::document::Document;
+
+#[derive(Debug, Clone)]
+struct Answer {
+    text: String,
+    percent: f32,
+    votes: u64,
+}
+
+#[derive(Debug, Clone)]
+enum AnswerMutation {
+    RemoveText,
+    EmptyText,
+    RemovePercent,
+    EmptyPercent,
+    InvalidPercent(String),
+    RemoveVotes,
+    EmptyVotes,
+    InvalidVotes(String),
+}
+
+impl AnswerMutation {
+    fn matches(&self, e: &AnswerResponseError) -> bool {
+        match self {
+            AnswerMutation::RemoveText | AnswerMutation::EmptyText => {
+                matches!(e, AnswerResponseError::MissingAnswerText)
+            }
+            AnswerMutation::RemovePercent | AnswerMutation::EmptyPercent => {
+                matches!(e, AnswerResponseError::MissingPercent)
+            }
+            AnswerMutation::InvalidPercent(_) => {
+                matches!(e, AnswerResponseError::InvalidPercent(_))
+            }
+            AnswerMutation::RemoveVotes | AnswerMutation::EmptyVotes => {
+                matches!(e, AnswerResponseError::MissingAnswerVotes)
+            }
+            AnswerMutation::InvalidVotes(_) => {
+                matches!(e, AnswerResponseError::InvalidAnswerVotes(_))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TotalMutation {
+    RemoveContainer,
+    RemoveSpan,
+    EmptySpan,
+    Invalid(String),
+}
+
+impl TotalMutation {
+    fn matches(&self, e: &HtmlResponseError) -> bool {
+        match self {
+            TotalMutation::RemoveContainer
+            | TotalMutation::RemoveSpan
+            | TotalMutation::EmptySpan => {
+                matches!(e, HtmlResponseError::MissingTotalVotes)
+            }
+            TotalMutation::Invalid(_) => matches!(e, HtmlResponseError::InvalidTotalVotes(_)),
+        }
+    }
+}
+
+/// Format a number with thousands separators, like polldaddy does
+fn format_votes(n: u64) -> String {
+    n.to_string()
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|chunk| std::str::from_utf8(chunk).unwrap())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn render_answer(answer: &Answer, mutation: Option<&AnswerMutation>) -> String {
+    let text = match mutation {
+        Some(AnswerMutation::RemoveText) => String::new(),
+        Some(AnswerMutation::EmptyText) => "<span class=\"pds-answer-text\"></span>".to_string(),
+        _ => format!("<span class=\"pds-answer-text\"> {} </span>", answer.text),
+    };
+
+    let percent = match mutation {
+        Some(AnswerMutation::RemovePercent) => String::new(),
+        Some(AnswerMutation::EmptyPercent) => {
+            "<span class=\"pds-feedback-per\"></span>".to_string()
+        }
+        Some(AnswerMutation::InvalidPercent(s)) => {
+            format!("<span class=\"pds-feedback-per\"> {}</span>", s)
+        }
+        _ => format!(
+            "<span class=\"pds-feedback-per\"> {}%</span>",
+            answer.percent
+        ),
+    };
+
+    let votes = match mutation {
+        Some(AnswerMutation::RemoveVotes) => String::new(),
+        Some(AnswerMutation::EmptyVotes) => {
+            "<span class=\"pds-feedback-votes\"></span>".to_string()
+        }
+        Some(AnswerMutation::InvalidVotes(s)) => {
+            format!("<span class=\"pds-feedback-votes\"> {}</span>", s)
+        }
+        _ => format!(
+            "<span class=\"pds-feedback-votes\"> ({} votes)</span>",
+            format_votes(answer.votes)
+        ),
+    };
+
+    format!(
+        "<div class=\"pds-feedback-group\"><label class=\"pds-feedback-label\">{}<span class=\"pds-feedback-result\">{}{}</span></label><span class=\"pds-clear\"></span><div class=\"pds-feedback-voted\"><span class=\"pds-answer-feedback-bar\"></span></div></div>",
+        text, percent, votes
+    )
+}
+
+fn render_results(
+    answers: &[(Answer, Option<AnswerMutation>)],
+    total_votes: u64,
+    total_mutation: Option<&TotalMutation>,
+    remove_answers: bool,
+) -> String {
+    let mut html = String::from("<div class=\"pds-box\"><div class=\"pds-box-outer\"><div class=\"pds-box-inner\"><div class=\"pds-box-top\"><div class=\"pds-question\"><div class=\"pds-question-inner\">Question?</div></div>");
+
+    if !remove_answers {
+        html.push_str("<div class=\"pds-answer\">");
+        for (answer, mutation) in answers {
+            html.push_str(&render_answer(answer, mutation.as_ref()));
+        }
+        html.push_str("</div>");
+    }
+
+    let total = match total_mutation {
+        Some(TotalMutation::RemoveContainer) => String::new(),
+        Some(TotalMutation::RemoveSpan) => format!(
+            "<div class=\"pds-total-votes\">Total Votes: {}</div>",
+            format_votes(total_votes)
+        ),
+        Some(TotalMutation::EmptySpan) => {
+            "<div class=\"pds-total-votes\">Total Votes: <span></span></div>".to_string()
+        }
+        Some(TotalMutation::Invalid(s)) => format!(
+            "<div class=\"pds-total-votes\">Total Votes: <span>{}</span></div>",
+            s
+        ),
+        None => format!(
+            "<div class=\"pds-total-votes\">Total Votes: <span>{}</span></div>",
+            format_votes(total_votes)
+        ),
+    };
+    html.push_str(&total);
+    html.push_str("</div></div></div></div>");
+
+    html
+}
+
+fn parse(html: &str) -> Result<HtmlResponse, HtmlResponseError> {
+    HtmlResponse::from_doc(&Document::from(html))
+}
+
+fn answer_strategy() -> impl Strategy<Value = Answer> {
+    (
+        "[A-Za-z0-9?!']([A-Za-z0-9?!' ]{0,20}[A-Za-z0-9?!'])?",
+        0.0f32..=100.0f32,
+        any::<u64>(),
+    )
+        .prop_map(|(text, percent, votes)| Answer {
+            text,
+            percent,
+            votes,
+        })
+}
+
+/// Strings that contain no digits, or too many to fit in a u64
+fn invalid_count_strategy() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-z ()]{0,10}", "[1-9][0-9]{20,30}"]
+}
+
+fn answer_mutation_strategy() -> impl Strategy<Value = AnswerMutation> {
+    prop_oneof![
+        Just(AnswerMutation::RemoveText),
+        Just(AnswerMutation::EmptyText),
+        Just(AnswerMutation::RemovePercent),
+        Just(AnswerMutation::EmptyPercent),
+        "[a-z.,]{0,6}%?"
+            .prop_filter("must not be a valid float", |s| {
+                s.trim_end_matches('%').parse::<f32>().is_err()
+            })
+            .prop_map(AnswerMutation::InvalidPercent),
+        Just(AnswerMutation::RemoveVotes),
+        Just(AnswerMutation::EmptyVotes),
+        invalid_count_strategy().prop_map(AnswerMutation::InvalidVotes),
+    ]
+}
+
+fn total_mutation_strategy() -> impl Strategy<Value = TotalMutation> {
+    prop_oneof![
+        Just(TotalMutation::RemoveContainer),
+        Just(TotalMutation::RemoveSpan),
+        Just(TotalMutation::EmptySpan),
+        invalid_count_strategy()
+            .prop_filter("must contain text", |s| !s.is_empty())
+            .prop_map(TotalMutation::Invalid),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn valid_results_round_trip(
+        answers in proptest::collection::vec(answer_strategy(), 0..8),
+        total_votes in any::<u64>(),
+    ) {
+        let answers = answers.into_iter().map(|a| (a, None)).collect::<Vec<_>>();
+        let html = render_results(&answers, total_votes, None, false);
+        let res = match parse(&html) {
+            Ok(res) => res,
+            Err(e) => return Err(TestCaseError::fail(format!("failed to parse: {:?}", e))),
+        };
+
+        prop_assert_eq!(res.get_total_votes(), total_votes);
+        prop_assert_eq!(res.get_answers().len(), answers.len());
+        for ((answer, _), parsed) in answers.iter().zip(res.get_answers().iter()) {
+            let parsed = match parsed {
+                Ok(parsed) => parsed,
+                Err(e) => return Err(TestCaseError::fail(format!("failed to parse {:?}: {:?}", answer, e))),
+            };
+
+            prop_assert_eq!(parsed.get_text(), answer.text.as_str());
+            prop_assert_eq!(parsed.get_percent(), answer.percent);
+            prop_assert_eq!(parsed.get_votes(), answer.votes);
+        }
+    }
+
+    #[test]
+    fn mutated_answers_map_to_errors(
+        answers in proptest::collection::vec(
+            (answer_strategy(), proptest::option::of(answer_mutation_strategy())),
+            1..8,
+        ),
+        total_votes in any::<u64>(),
+    ) {
+        let html = render_results(&answers, total_votes, None, false);
+        let res = match parse(&html) {
+            Ok(res) => res,
+            Err(e) => return Err(TestCaseError::fail(format!("failed to parse: {:?}", e))),
+        };
+
+        prop_assert_eq!(res.get_answers().len(), answers.len());
+        for ((answer, mutation), parsed) in answers.iter().zip(res.get_answers().iter()) {
+            match (mutation, parsed) {
+                (None, Ok(parsed)) => {
+                    prop_assert_eq!(parsed.get_text(), answer.text.as_str());
+                    prop_assert_eq!(parsed.get_percent(), answer.percent);
+                    prop_assert_eq!(parsed.get_votes(), answer.votes);
+                }
+                (Some(mutation), Err(e)) => {
+                    prop_assert!(mutation.matches(e), "{:?} gave {:?}", mutation, e);
+                }
+                (mutation, parsed) => {
+                    return Err(TestCaseError::fail(format!("{:?} gave {:?}", mutation, parsed)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mutated_results_map_to_errors(
+        answers in proptest::collection::vec(answer_strategy(), 0..4),
+        total_votes in any::<u64>(),
+        total_mutation in proptest::option::of(total_mutation_strategy()),
+        remove_answers in any::<bool>(),
+    ) {
+        prop_assume!(total_mutation.is_some() || remove_answers);
+
+        let answers = answers.into_iter().map(|a| (a, None)).collect::<Vec<_>>();
+        let html = render_results(&answers, total_votes, total_mutation.as_ref(), remove_answers);
+        match (&total_mutation, parse(&html)) {
+            (Some(mutation), Err(e)) => {
+                prop_assert!(mutation.matches(&e), "{:?} gave {:?}", mutation, e);
+            }
+            (None, Err(e)) => prop_assert!(matches!(e, HtmlResponseError::MissingAnswers), "got {:?}", e),
+            (mutation, Ok(res)) => {
+                return Err(TestCaseError::fail(format!("{:?} parsed as {:?}", mutation, res)));
+            }
+        }
+    }
+
+    #[test]
+    fn arbitrary_markup_does_not_panic(html in any::<String>()) {
+        let _ = parse(&html);
+    }
+
+    #[test]
+    fn arbitrary_results_markup_does_not_panic(
+        html in "(<div class=\"pds-(answer|feedback-group|answer-text|feedback-per|feedback-votes|total-votes)\">|</div>|<span>|</span>|[a-z0-9 ,.%()]{0,8}){0,40}"
+    ) {
+        let _ = parse(&html);
+    }
+}